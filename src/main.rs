@@ -1,7 +1,18 @@
+use std::thread;
+use std::time::Duration;
+
+use crossterm::event::{self, Event, KeyCode};
+use crossterm::terminal as term;
 use macroquad::prelude::*;
 
+mod render;
+mod rule;
+mod terminal;
 mod world;
 
+use render::MacroquadRenderer;
+use terminal::TerminalRenderer;
+
 fn window_conf() -> Conf {
     Conf {
         window_title: "AutoCell".to_owned(),
@@ -12,9 +23,17 @@ fn window_conf() -> Conf {
     }
 }
 
-#[macroquad::main(window_conf)]
-async fn main() {
+fn main() {
+    if std::env::args().any(|arg| arg == "--headless") {
+        run_headless();
+    } else {
+        macroquad::Window::from_config(window_conf(), run_windowed());
+    }
+}
+
+async fn run_windowed() {
     let mut world = world::SandWorld::new(80, 80, 10);
+    let mut renderer = MacroquadRenderer;
 
     loop {
         clear_background(WHITE);
@@ -23,10 +42,51 @@ async fn main() {
 
         world.update();
 
-        world.commit_cells();
+        world.tick(get_frame_time());
 
-        world.draw();
+        world.draw(&mut renderer);
 
         next_frame().await
     }
 }
+
+/// How many grid cells an arrow key pans the headless viewport by.
+const SCROLL_STEP: i32 = 4;
+
+/// Runs the automaton without opening a window, rendering block glyphs to
+/// stdout instead. Handy over SSH or for recording CI snapshots. Raw mode is
+/// enabled so arrow keys can pan the viewport over worlds bigger than the
+/// terminal without needing Enter; `q`/Esc exits and restores the terminal.
+fn run_headless() {
+    const TICK: f32 = 1.0 / 30.0;
+
+    let mut world = world::SandWorld::new(80, 80, 10);
+    let mut renderer = TerminalRenderer::new();
+
+    if let Err(err) = term::enable_raw_mode() {
+        eprintln!("failed to enable raw mode: {err}");
+    }
+
+    loop {
+        while event::poll(Duration::ZERO).unwrap_or(false) {
+            let Ok(Event::Key(key)) = event::read() else {
+                continue;
+            };
+            match key.code {
+                KeyCode::Up => renderer.scroll(0, -SCROLL_STEP),
+                KeyCode::Down => renderer.scroll(0, SCROLL_STEP),
+                KeyCode::Left => renderer.scroll(-SCROLL_STEP, 0),
+                KeyCode::Right => renderer.scroll(SCROLL_STEP, 0),
+                KeyCode::Char('q') | KeyCode::Esc => {
+                    let _ = term::disable_raw_mode();
+                    return;
+                }
+                _ => {}
+            }
+        }
+
+        world.tick(TICK);
+        world.draw(&mut renderer);
+        thread::sleep(Duration::from_secs_f32(TICK));
+    }
+}
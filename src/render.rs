@@ -0,0 +1,31 @@
+use crate::world::{CellClass, GridView};
+
+/// Something that can draw a `GridView` each frame. `SandWorld::draw` doesn't
+/// care which implementation it's handed; `MacroquadRenderer` and
+/// `terminal::TerminalRenderer` are the two this crate ships.
+pub trait Renderer {
+    fn render(&mut self, grid: GridView);
+}
+
+/// Draws straight into the macroquad window via `draw_rectangle`, same as
+/// `SandWorld::draw` did before rendering was made pluggable.
+pub struct MacroquadRenderer;
+
+impl Renderer for MacroquadRenderer {
+    fn render(&mut self, grid: GridView) {
+        use macroquad::prelude::*;
+
+        for (index, class, (ox, oy)) in grid.iter() {
+            let color = match class {
+                CellClass::Empty => continue,
+                CellClass::Sand => YELLOW,
+                CellClass::Water => BLUE,
+                CellClass::Rock => GRAY,
+                CellClass::Smoke => LIGHTGRAY,
+            };
+            let x = (index % grid.width) as f32 * grid.scale as f32 + ox as f32;
+            let y = (index / grid.width) as f32 * grid.scale as f32 + oy as f32;
+            draw_rectangle(x, y, grid.scale as f32, grid.scale as f32, color);
+        }
+    }
+}
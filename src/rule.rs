@@ -0,0 +1,336 @@
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::world::CellClass;
+
+/// What a pattern cell requires of the world in order to match.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RuleCellFrom {
+    /// Matches any cell, regardless of class.
+    Any,
+    /// Matches only the given class.
+    One(CellClass),
+    /// Matches any class that belongs to the named group.
+    Group(usize),
+}
+
+impl RuleCellFrom {
+    fn matches(&self, class: CellClass, groups: &[Vec<CellClass>]) -> bool {
+        match self {
+            RuleCellFrom::Any => true,
+            RuleCellFrom::One(want) => *want == class,
+            RuleCellFrom::Group(g) => groups
+                .get(*g)
+                .map(|members| members.contains(&class))
+                .unwrap_or(false),
+        }
+    }
+}
+
+/// What a pattern cell should be rewritten to once the rule matches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RuleCellTo {
+    /// Leave the cell as it was.
+    None,
+    /// Overwrite the cell with the given class.
+    One(CellClass),
+    /// Overwrite the cell with a random class drawn from the named group.
+    GroupRandom(usize),
+    /// Overwrite the cell with whatever class matched at another position in the pattern.
+    Copy(usize),
+}
+
+impl RuleCellTo {
+    fn resolve(&self, snapshot: &[CellClass], groups: &[Vec<CellClass>]) -> Option<CellClass> {
+        match self {
+            RuleCellTo::None => None,
+            RuleCellTo::One(class) => Some(*class),
+            RuleCellTo::GroupRandom(g) => groups.get(*g).and_then(|members| {
+                if members.is_empty() {
+                    None
+                } else {
+                    Some(members[rand::gen_range(0, members.len())])
+                }
+            }),
+            RuleCellTo::Copy(index) => snapshot.get(*index).copied(),
+        }
+    }
+}
+
+/// A `width x height` grid of `(RuleCellFrom, RuleCellTo)` pairs. The top-left
+/// corner of the grid is anchored at the world position being tested, so a
+/// pattern can only look right and down from there; mirrored variants (see
+/// `Rule::flip_x`/`flip_y`) are how the other directions get covered.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubRule {
+    pub width: usize,
+    pub height: usize,
+    pub cells: Vec<(RuleCellFrom, RuleCellTo)>,
+}
+
+impl SubRule {
+    fn at(&self, col: usize, row: usize) -> &(RuleCellFrom, RuleCellTo) {
+        &self.cells[row * self.width + col]
+    }
+
+    /// Tries to match the pattern against the world using `get` to read the
+    /// class at a pattern-local `(col, row)` offset. Returns the matched
+    /// classes (row-major, same order as `cells`) on success.
+    pub fn try_match(
+        &self,
+        groups: &[Vec<CellClass>],
+        get: impl Fn(usize, usize) -> Option<CellClass>,
+    ) -> Option<Vec<CellClass>> {
+        let mut snapshot = Vec::with_capacity(self.width * self.height);
+        for row in 0..self.height {
+            for col in 0..self.width {
+                let class = get(col, row)?;
+                let (from, _) = self.at(col, row);
+                if !from.matches(class, groups) {
+                    return None;
+                }
+                snapshot.push(class);
+            }
+        }
+        Some(snapshot)
+    }
+
+    /// Resolves the `to` side of every pattern cell given the classes that
+    /// matched on the `from` side. `None` entries mean "leave unchanged".
+    pub fn resolve(&self, snapshot: &[CellClass], groups: &[Vec<CellClass>]) -> Vec<Option<CellClass>> {
+        self.cells
+            .iter()
+            .map(|(_, to)| to.resolve(snapshot, groups))
+            .collect()
+    }
+
+    /// The pattern-local source index a position's output was `Copy`'d from,
+    /// if it was a `Copy` rather than a literal/random rewrite. Lets the
+    /// caller tell "this cell's new content moved here from elsewhere in the
+    /// pattern" apart from "this cell just changed class in place".
+    pub fn copy_source(&self, position: usize) -> Option<usize> {
+        match self.cells[position].1 {
+            RuleCellTo::Copy(source) => Some(source),
+            _ => None,
+        }
+    }
+
+    fn remap(&self, new_width: usize, new_height: usize, f: impl Fn(usize, usize) -> (usize, usize)) -> SubRule {
+        let index_of = |row: usize, col: usize| -> usize {
+            let (new_row, new_col) = f(row, col);
+            new_row * new_width + new_col
+        };
+        let mut cells = vec![(RuleCellFrom::Any, RuleCellTo::None); new_width * new_height];
+        for row in 0..self.height {
+            for col in 0..self.width {
+                let (from, to) = *self.at(col, row);
+                let to = match to {
+                    RuleCellTo::Copy(old_index) => {
+                        let old_row = old_index / self.width;
+                        let old_col = old_index % self.width;
+                        RuleCellTo::Copy(index_of(old_row, old_col))
+                    }
+                    other => other,
+                };
+                cells[index_of(row, col)] = (from, to);
+            }
+        }
+        SubRule {
+            width: new_width,
+            height: new_height,
+            cells,
+        }
+    }
+
+    fn flipped_x(&self) -> SubRule {
+        self.remap(self.width, self.height, |row, col| (row, self.width - 1 - col))
+    }
+
+    fn flipped_y(&self) -> SubRule {
+        self.remap(self.width, self.height, |row, col| (self.height - 1 - row, col))
+    }
+
+    fn rotated(&self) -> SubRule {
+        self.remap(self.height, self.width, |row, col| (col, self.height - 1 - row))
+    }
+}
+
+/// A pattern rewrite rule: if `pattern` matches at a position and a random
+/// roll survives `failrate` (0-255, applied as `failrate/256` chance to do
+/// nothing), `pattern`'s outputs are written into `SandWorld::changes`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Rule {
+    pub pattern: SubRule,
+    #[serde(default)]
+    pub flip_x: bool,
+    #[serde(default)]
+    pub flip_y: bool,
+    #[serde(default)]
+    pub rotate: bool,
+    #[serde(default)]
+    pub failrate: u8,
+}
+
+impl Rule {
+    /// Expands this rule into every pattern variant implied by its
+    /// `flip_x`/`flip_y`/`rotate` flags, deduplicating identical patterns.
+    pub fn variants(&self) -> Vec<SubRule> {
+        let mut patterns = vec![self.pattern.clone()];
+
+        if self.flip_x {
+            let flipped: Vec<SubRule> = patterns.iter().map(SubRule::flipped_x).collect();
+            patterns.extend(flipped);
+        }
+        if self.flip_y {
+            let flipped: Vec<SubRule> = patterns.iter().map(SubRule::flipped_y).collect();
+            patterns.extend(flipped);
+        }
+        if self.rotate {
+            let mut rotated = Vec::new();
+            for p in &patterns {
+                let mut current = p.rotated();
+                for _ in 0..3 {
+                    rotated.push(current.clone());
+                    current = current.rotated();
+                }
+            }
+            patterns.extend(rotated);
+        }
+
+        let mut unique: Vec<SubRule> = Vec::new();
+        for pattern in patterns {
+            if !unique.contains(&pattern) {
+                unique.push(pattern);
+            }
+        }
+        unique
+    }
+}
+
+/// One expanded, ready-to-match pattern: `rule` is the index into
+/// `Ruleset::rules` it came from, `variant` is its position within that
+/// rule's own `Rule::variants()` list.
+pub struct RuleVariant {
+    pub rule: usize,
+    pub variant: usize,
+    pub pattern: SubRule,
+    pub failrate: u8,
+}
+
+/// A named collection of classes that `RuleCellFrom::Group`/`RuleCellTo::GroupRandom`
+/// can refer to by index, plus the rules themselves.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Ruleset {
+    #[serde(default)]
+    pub groups: Vec<Vec<CellClass>>,
+    pub rules: Vec<Rule>,
+}
+
+impl Ruleset {
+    pub fn from_json(data: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(data)
+    }
+
+    /// The ruleset shipped with AutoCel, describing the classic sand/water/smoke
+    /// behavior in data instead of hardcoded match ladders.
+    pub fn default_ruleset() -> Self {
+        Self::from_json(include_str!("../assets/default_ruleset.json"))
+            .expect("assets/default_ruleset.json should parse")
+    }
+
+    /// Expands every rule into its transformed variants, ready to be matched
+    /// in priority order (earlier rules are tried first).
+    pub fn expand(&self) -> Vec<RuleVariant> {
+        let mut variants = Vec::new();
+        for (rule_index, rule) in self.rules.iter().enumerate() {
+            for (variant_index, pattern) in rule.variants().into_iter().enumerate() {
+                variants.push(RuleVariant {
+                    rule: rule_index,
+                    variant: variant_index,
+                    pattern,
+                    failrate: rule.failrate,
+                });
+            }
+        }
+        variants
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn variants_dedupe_identical_transforms() {
+        // Every transform of a 1x1 pattern maps back to itself, so no flag
+        // combination should ever produce more than one variant.
+        let rule = Rule {
+            pattern: SubRule {
+                width: 1,
+                height: 1,
+                cells: vec![(RuleCellFrom::One(CellClass::Sand), RuleCellTo::One(CellClass::Water))],
+            },
+            flip_x: true,
+            flip_y: true,
+            rotate: true,
+            failrate: 0,
+        };
+        assert_eq!(rule.variants().len(), 1);
+    }
+
+    #[test]
+    fn flip_x_mirrors_pattern_and_remaps_copy_targets() {
+        // "sand swaps right into empty" flipped horizontally should give the
+        // mirrored "sand swaps left into empty", with the `Copy` indices
+        // still pointing at each other rather than at themselves.
+        let rule = Rule {
+            pattern: SubRule {
+                width: 2,
+                height: 1,
+                cells: vec![
+                    (RuleCellFrom::One(CellClass::Sand), RuleCellTo::Copy(1)),
+                    (RuleCellFrom::One(CellClass::Empty), RuleCellTo::Copy(0)),
+                ],
+            },
+            flip_x: true,
+            flip_y: false,
+            rotate: false,
+            failrate: 0,
+        };
+        let variants = rule.variants();
+        assert_eq!(variants.len(), 2);
+
+        let flipped = &variants[1];
+        assert_eq!(flipped.cells[0].0, RuleCellFrom::One(CellClass::Empty));
+        assert_eq!(flipped.cells[1].0, RuleCellFrom::One(CellClass::Sand));
+        assert_eq!(flipped.cells[0].1, RuleCellTo::Copy(1));
+        assert_eq!(flipped.cells[1].1, RuleCellTo::Copy(0));
+
+        let groups: Vec<Vec<CellClass>> = Vec::new();
+        let classes = [CellClass::Empty, CellClass::Sand];
+        let snapshot = flipped
+            .try_match(&groups, |col, _row| Some(classes[col]))
+            .expect("flipped pattern should match Empty|Sand");
+        let resolved = flipped.resolve(&snapshot, &groups);
+        assert_eq!(resolved, vec![Some(CellClass::Sand), Some(CellClass::Empty)]);
+    }
+
+    #[test]
+    fn rotate_expands_a_vertical_pattern_into_all_four_axes() {
+        let rule = Rule {
+            pattern: SubRule {
+                width: 1,
+                height: 2,
+                cells: vec![
+                    (RuleCellFrom::One(CellClass::Sand), RuleCellTo::Copy(1)),
+                    (RuleCellFrom::One(CellClass::Empty), RuleCellTo::Copy(0)),
+                ],
+            },
+            flip_x: false,
+            flip_y: false,
+            rotate: true,
+            failrate: 0,
+        };
+        assert_eq!(rule.variants().len(), 4);
+    }
+}
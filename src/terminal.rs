@@ -0,0 +1,175 @@
+use std::io::{self, Write};
+
+use crossterm::cursor::MoveTo;
+use crossterm::style::{Attribute, Color, Print, ResetColor, SetAttribute, SetBackgroundColor, SetForegroundColor};
+use crossterm::{queue, terminal};
+
+use crate::render::Renderer;
+use crate::world::{CellClass, GridView};
+
+/// Display attributes for a `TermCell` that aren't captured by its colors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+struct TermAttributes {
+    bold: bool,
+}
+
+/// One character cell of a terminal frame: a glyph plus the foreground,
+/// background and attributes it's drawn with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct TermCell {
+    glyph: char,
+    fg: Color,
+    bg: Color,
+    attributes: TermAttributes,
+}
+
+impl TermCell {
+    fn blank() -> Self {
+        Self {
+            glyph: ' ',
+            fg: Color::Reset,
+            bg: Color::Reset,
+            attributes: TermAttributes::default(),
+        }
+    }
+}
+
+/// A full terminal frame, one `TermCell` per character cell. Diffed against
+/// the previous frame on `flush` so only cells that actually changed are
+/// written to the output.
+struct CellBuffer {
+    width: usize,
+    height: usize,
+    cells: Vec<TermCell>,
+}
+
+impl CellBuffer {
+    fn blank(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![TermCell::blank(); width * height],
+        }
+    }
+
+    fn set(&mut self, col: usize, row: usize, cell: TermCell) {
+        if col < self.width && row < self.height {
+            self.cells[row * self.width + col] = cell;
+        }
+    }
+
+    /// Writes every cell that differs from `previous` to `out`, then parks
+    /// the cursor below the frame and flushes.
+    fn flush(&self, previous: &CellBuffer, out: &mut impl Write) -> io::Result<()> {
+        for row in 0..self.height {
+            for col in 0..self.width {
+                let index = row * self.width + col;
+                let cell = self.cells[index];
+                if previous.cells.get(index) == Some(&cell) {
+                    continue;
+                }
+                queue!(
+                    out,
+                    MoveTo(col as u16, row as u16),
+                    SetAttribute(Attribute::Reset),
+                    SetForegroundColor(cell.fg),
+                    SetBackgroundColor(cell.bg),
+                )?;
+                if cell.attributes.bold {
+                    queue!(out, SetAttribute(Attribute::Bold))?;
+                }
+                queue!(out, Print(cell.glyph))?;
+            }
+        }
+        queue!(out, ResetColor, MoveTo(0, self.height as u16))?;
+        out.flush()
+    }
+}
+
+/// The block glyph and color a `CellClass` renders as, or `None` for classes
+/// that leave the terminal cell blank.
+fn glyph_for(class: CellClass) -> Option<TermCell> {
+    let fg = match class {
+        CellClass::Empty => return None,
+        CellClass::Sand => Color::Yellow,
+        CellClass::Water => Color::Blue,
+        CellClass::Rock => Color::DarkGrey,
+        CellClass::Smoke => Color::Grey,
+    };
+    Some(TermCell {
+        glyph: '█',
+        fg,
+        ..TermCell::blank()
+    })
+}
+
+/// Renders the grid as colored block glyphs written to `stdout` instead of a
+/// window, for running and recording the automaton over SSH or in CI
+/// snapshot tests. Worlds larger than the terminal are shown through a
+/// scrollable viewport rather than squeezed to fit.
+pub struct TerminalRenderer<W: Write> {
+    out: W,
+    previous: Option<CellBuffer>,
+    viewport_x: usize,
+    viewport_y: usize,
+}
+
+impl TerminalRenderer<io::Stdout> {
+    pub fn new() -> Self {
+        Self::with_writer(io::stdout())
+    }
+}
+
+impl<W: Write> TerminalRenderer<W> {
+    pub fn with_writer(out: W) -> Self {
+        Self {
+            out,
+            previous: None,
+            viewport_x: 0,
+            viewport_y: 0,
+        }
+    }
+
+    /// Pans the viewport by `(dx, dy)` grid cells; out-of-range offsets are
+    /// clamped back into the grid on the next `render`.
+    pub fn scroll(&mut self, dx: i32, dy: i32) {
+        self.viewport_x = (self.viewport_x as i32 + dx).max(0) as usize;
+        self.viewport_y = (self.viewport_y as i32 + dy).max(0) as usize;
+    }
+}
+
+impl<W: Write> Renderer for TerminalRenderer<W> {
+    fn render(&mut self, grid: GridView) {
+        let (cols, rows) = terminal::size()
+            .map(|(c, r)| (c as usize, r as usize))
+            .unwrap_or((grid.width, grid.height));
+        let view_width = cols.min(grid.width);
+        let view_height = rows.min(grid.height);
+        self.viewport_x = self.viewport_x.min(grid.width.saturating_sub(view_width));
+        self.viewport_y = self.viewport_y.min(grid.height.saturating_sub(view_height));
+
+        let mut frame = CellBuffer::blank(view_width, view_height);
+        for (index, class, _offset) in grid.iter() {
+            let (x, y) = (index % grid.width, index / grid.width);
+            if x < self.viewport_x || y < self.viewport_y {
+                continue;
+            }
+            let (col, row) = (x - self.viewport_x, y - self.viewport_y);
+            if col >= view_width || row >= view_height {
+                continue;
+            }
+            if let Some(cell) = glyph_for(class) {
+                frame.set(col, row, cell);
+            }
+        }
+
+        let previous = self
+            .previous
+            .take()
+            .unwrap_or_else(|| CellBuffer::blank(view_width, view_height));
+        if let Err(err) = frame.flush(&previous, &mut self.out) {
+            eprintln!("failed to flush terminal frame: {err}");
+        }
+        self.previous = Some(frame);
+    }
+}
@@ -1,262 +1,634 @@
 use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
 
-use bitflags::bitflags;
 use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
-enum CellClass {
+use crate::render::Renderer;
+use crate::rule::{RuleVariant, Ruleset};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CellClass {
     Empty,
     Sand,
     Water,
     Rock,
-    Smoke, // <-- new element
+    Smoke,
 }
 
-bitflags! {
-    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-    struct CellProperties: u32 {
-        const NONE          = 0b00000000;
-        const MOVEDOWN      = 0b00000001;
-        const MOVEDOWNSIDE  = 0b00000010;
-        const MOVESIDE      = 0b00000100;
-        const MOVEUP        = 0b00001000; // <-- for upward movement
-        const MOVEUPSIDE    = 0b00010000; // <-- for diagonal upward movement
-    }
-}
-
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 struct Cell {
     class: CellClass,
-    properties: CellProperties,
 }
 
 impl Cell {
     fn empty() -> Self {
         Self {
             class: CellClass::Empty,
-            properties: CellProperties::NONE,
         }
     }
 
-    fn sand() -> Self {
+    fn of(class: CellClass) -> Self {
+        Self { class }
+    }
+}
+
+/// Cells per side of a `Chunk`.
+const CHUNK_SIZE: usize = 64;
+
+/// A fixed `CHUNK_SIZE x CHUNK_SIZE` tile of the grid, allocated lazily the
+/// first time something writes into it.
+struct Chunk {
+    cells: Box<[[Cell; CHUNK_SIZE]; CHUNK_SIZE]>,
+    /// How many of this chunk's cells are non-`Empty`, so `is_empty` doesn't
+    /// need a full scan.
+    live_cells: usize,
+    /// Whether a write landed in this chunk since `World` last cleared
+    /// activity; an empty, inactive chunk is indistinguishable from one that
+    /// was never allocated, so it gets reclaimed.
+    active: bool,
+}
+
+impl Chunk {
+    fn empty() -> Self {
         Self {
-            class: CellClass::Sand,
-            properties: CellProperties::MOVEDOWN | CellProperties::MOVEDOWNSIDE,
+            cells: Box::new([[Cell::empty(); CHUNK_SIZE]; CHUNK_SIZE]),
+            live_cells: 0,
+            active: false,
         }
     }
 
-    fn water() -> Self {
-        Self {
-            class: CellClass::Water,
-            properties: CellProperties::MOVEDOWN
-                | CellProperties::MOVESIDE
-                | CellProperties::MOVEDOWNSIDE,
+    fn is_empty(&self) -> bool {
+        self.live_cells == 0
+    }
+
+    fn get(&self, x: usize, y: usize) -> Cell {
+        self.cells[y][x]
+    }
+
+    fn set(&mut self, x: usize, y: usize, cell: Cell) {
+        let previous = self.cells[y][x];
+        if previous.class == CellClass::Empty && cell.class != CellClass::Empty {
+            self.live_cells += 1;
+        } else if previous.class != CellClass::Empty && cell.class == CellClass::Empty {
+            self.live_cells -= 1;
         }
+        self.cells[y][x] = cell;
+        self.active = true;
     }
+}
+
+/// The grid as a sparse collection of `Chunk`s keyed by chunk coordinates,
+/// so worlds far bigger than would fit in a dense `Vec<Cell>` only pay for
+/// the regions something has actually touched. Untouched positions read
+/// back as `Cell::empty()` without allocating anything.
+struct World {
+    chunks: HashMap<(i32, i32), Chunk>,
+}
 
-    fn rock() -> Self {
+impl World {
+    fn new() -> Self {
         Self {
-            class: CellClass::Rock,
-            properties: CellProperties::NONE,
+            chunks: HashMap::new(),
         }
     }
 
-    // New: smoke moves upward
-    fn smoke() -> Self {
+    fn split(x: usize, y: usize) -> ((i32, i32), (usize, usize)) {
+        (
+            ((x / CHUNK_SIZE) as i32, (y / CHUNK_SIZE) as i32),
+            (x % CHUNK_SIZE, y % CHUNK_SIZE),
+        )
+    }
+
+    fn get(&self, x: usize, y: usize) -> Cell {
+        let (chunk_pos, (lx, ly)) = Self::split(x, y);
+        self.chunks
+            .get(&chunk_pos)
+            .map(|chunk| chunk.get(lx, ly))
+            .unwrap_or_else(Cell::empty)
+    }
+
+    fn set(&mut self, x: usize, y: usize, cell: Cell) {
+        let (chunk_pos, (lx, ly)) = Self::split(x, y);
+        self.chunks
+            .entry(chunk_pos)
+            .or_insert_with(Chunk::empty)
+            .set(lx, ly, cell);
+    }
+
+    /// Marks every chunk inactive, so this tick's writes are the only ones
+    /// that can mark them active again before `reclaim_dormant` runs.
+    fn clear_activity(&mut self) {
+        for chunk in self.chunks.values_mut() {
+            chunk.active = false;
+        }
+    }
+
+    /// Drops chunks that are both empty and untouched this tick: they're
+    /// indistinguishable from chunks that were never allocated, so freeing
+    /// them is free to do and keeps memory tied to active regions only.
+    fn reclaim_dormant(&mut self) {
+        self.chunks.retain(|_, chunk| chunk.active || !chunk.is_empty());
+    }
+
+    /// Iterates every non-`Empty` cell within `[0, width) x [0, height)` as
+    /// `(flat index, class)`, skipping whole chunks that are empty instead
+    /// of visiting each of their cells individually.
+    fn live_cells(&self, width: usize, height: usize) -> impl Iterator<Item = (usize, CellClass)> + '_ {
+        self.chunks.iter().flat_map(move |(&(cx, cy), chunk)| {
+            let base_x = cx as usize * CHUNK_SIZE;
+            let base_y = cy as usize * CHUNK_SIZE;
+            let cell_count = if chunk.is_empty() { 0 } else { CHUNK_SIZE * CHUNK_SIZE };
+            (0..cell_count).filter_map(move |i| {
+                let (lx, ly) = (i % CHUNK_SIZE, i / CHUNK_SIZE);
+                let (x, y) = (base_x + lx, base_y + ly);
+                if x >= width || y >= height {
+                    return None;
+                }
+                let cell = chunk.get(lx, ly);
+                (cell.class != CellClass::Empty).then_some((y * width + x, cell.class))
+            })
+        })
+    }
+}
+
+/// Tracks, for one expanded rule variant, every grid position that currently
+/// matches it (as of the last `commit_cells`).
+struct RuleCache {
+    #[allow(dead_code)]
+    rule: usize,
+    #[allow(dead_code)]
+    variant: usize,
+    matches: Vec<usize>,
+}
+
+/// How many simulation steps run per second, independent of render FPS.
+const TICK_RATE: f32 = 30.0;
+const TICK_DURATION: f32 = 1.0 / TICK_RATE;
+
+/// Destination cell index -> pixel offset from its old position.
+type BlockOffsets = HashMap<usize, (i32, i32)>;
+
+/// Shrinks `block_offsets` toward zero given how far into the tick `progress` is.
+type ShrinkFn = Box<dyn Fn(&BlockOffsets, f32) -> BlockOffsets>;
+
+/// Smooths rendering of cell moves between simulation ticks. `block_offsets`
+/// holds, for each destination cell a tick moved something into, the pixel
+/// offset from its old position `draw` should still show it at; `progress`
+/// (0..1 over `TICK_DURATION`) drives a function that shrinks those offsets
+/// toward zero so motion reads as sliding rather than teleporting.
+struct AnimationState {
+    block_offsets: BlockOffsets,
+    progress: f32,
+    shrink: ShrinkFn,
+}
+
+impl AnimationState {
+    fn new() -> Self {
         Self {
-            class: CellClass::Smoke,
-            // It will try to move straight up first, then diagonally up:
-            properties: CellProperties::MOVEUP | CellProperties::MOVEUPSIDE,
+            block_offsets: HashMap::new(),
+            progress: 1.0,
+            shrink: Box::new(|offsets, progress| {
+                let remaining = 1.0 - progress;
+                offsets
+                    .iter()
+                    .map(|(&index, &(dx, dy))| {
+                        (
+                            index,
+                            (
+                                (dx as f32 * remaining).round() as i32,
+                                (dy as f32 * remaining).round() as i32,
+                            ),
+                        )
+                    })
+                    .collect()
+            }),
+        }
+    }
+
+    /// Starts a new animation from the moves recorded by the tick that just committed.
+    fn start(&mut self, block_offsets: BlockOffsets) {
+        self.block_offsets = block_offsets;
+        self.progress = 0.0;
+    }
+
+    fn is_done(&self) -> bool {
+        self.progress >= 1.0
+    }
+
+    /// Sets progress directly to `tick_accumulator / TICK_DURATION`. Unlike
+    /// accumulating the frame's `delta` each call, this stays correct across
+    /// a `start()` reset: the fraction of the tick already elapsed is exactly
+    /// how far leftover accumulator time has gotten since the last tick
+    /// boundary, regardless of the render frame rate.
+    fn seed_progress(&mut self, progress: f32) {
+        self.progress = progress.min(1.0);
+    }
+
+    /// The offsets `draw` should apply right now, empty once the animation has finished.
+    fn current_offsets(&self) -> BlockOffsets {
+        if self.is_done() {
+            HashMap::new()
+        } else {
+            (self.shrink)(&self.block_offsets, self.progress)
         }
     }
 }
+
+/// A read-only snapshot of the grid for a `Renderer` to draw: the live cell
+/// classes plus each one's current animation offset in pixels, without
+/// exposing `SandWorld`'s own mutable state.
+pub struct GridView<'a> {
+    pub width: usize,
+    pub height: usize,
+    pub scale: usize,
+    cells: &'a World,
+    offsets: BlockOffsets,
+}
+
+impl<'a> GridView<'a> {
+    /// Iterates every non-`Empty` cell as `(flat index, class, pixel offset)`.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, CellClass, (i32, i32))> + '_ {
+        self.cells
+            .live_cells(self.width, self.height)
+            .map(move |(i, class)| (i, class, self.offsets.get(&i).copied().unwrap_or((0, 0))))
+    }
+}
+
 pub struct SandWorld {
-    brush: Cell,
+    brush: CellClass,
     width: usize,
     height: usize,
     scale: usize,
-    cells: Vec<Cell>,
-    changes: HashMap<usize, Vec<usize>>,
+    cells: World,
+    changes: HashMap<usize, Cell>,
+    /// Destination index -> source index, for changes this tick whose new
+    /// class was `Copy`'d from elsewhere in the matched pattern rather than
+    /// written literally. Drives `animation`'s slide effect.
+    moves: HashMap<usize, usize>,
+    ruleset: Ruleset,
+    variants: Vec<RuleVariant>,
+    cache: Vec<RuleCache>,
+    /// For each cell, the indices into `cache` that currently record it as a
+    /// matching anchor position. Lets `invalidate_and_rescan` drop stale
+    /// matches by searching only the caches listed for that cell instead of
+    /// every cache.
+    match_cache: Vec<Vec<usize>>,
+    max_rule_width: usize,
+    max_rule_height: usize,
     water_cells: usize,
+    /// Leftover render time not yet consumed by a simulation tick.
+    tick_accumulator: f32,
+    animation: AnimationState,
+}
+
+/// On-disk form of a `SandWorld`: its dimensions, cell contents and the
+/// ruleset driving it, so a scene can be authored or tweaked by hand.
+#[derive(Serialize, Deserialize)]
+struct WorldSnapshot {
+    width: usize,
+    height: usize,
+    scale: usize,
+    cells: Vec<Cell>,
+    ruleset: Ruleset,
 }
 
 impl SandWorld {
+    const SAVE_PATH: &'static str = "autocel_save.json5";
+
     pub fn new(width: usize, height: usize, scale: usize) -> Self {
         let mut cells = vec![Cell::empty(); width * height];
+        cells[0] = Cell::of(CellClass::Sand);
 
-        cells[0] = Cell {
-            class: CellClass::Sand,
-            properties: CellProperties::MOVEDOWN,
-        };
-        let changes = HashMap::new();
-        let brush = Cell::sand();
-        Self {
-            brush,
+        Self::build(width, height, scale, cells, Ruleset::default_ruleset())
+    }
+
+    fn build(width: usize, height: usize, scale: usize, cells: Vec<Cell>, ruleset: Ruleset) -> Self {
+        let variants = ruleset.expand();
+        let max_rule_width = variants.iter().map(|v| v.pattern.width).max().unwrap_or(1);
+        let max_rule_height = variants.iter().map(|v| v.pattern.height).max().unwrap_or(1);
+        let cache = variants
+            .iter()
+            .map(|v| RuleCache {
+                rule: v.rule,
+                variant: v.variant,
+                matches: Vec::new(),
+            })
+            .collect();
+        let water_cells = cells.iter().filter(|c| c.class == CellClass::Water).count();
+
+        let mut chunked = World::new();
+        for (index, cell) in cells.into_iter().enumerate() {
+            if cell.class != CellClass::Empty {
+                chunked.set(index % width, index / width, cell);
+            }
+        }
+
+        let mut world = Self {
+            brush: CellClass::Sand,
             width,
             height,
             scale,
-            cells,
-            changes,
-            water_cells: 0,
+            cells: chunked,
+            changes: HashMap::new(),
+            moves: HashMap::new(),
+            ruleset,
+            variants,
+            cache,
+            match_cache: vec![Vec::new(); width * height],
+            max_rule_width,
+            max_rule_height,
+            water_cells,
+            tick_accumulator: 0.0,
+            animation: AnimationState::new(),
+        };
+        world.rescan_all();
+        world
+    }
+
+    /// Dumps the current canvas and active ruleset to `path` as json5.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut cells = vec![Cell::empty(); self.width * self.height];
+        for (index, class) in self.cells.live_cells(self.width, self.height) {
+            cells[index] = Cell::of(class);
         }
+        let snapshot = WorldSnapshot {
+            width: self.width,
+            height: self.height,
+            scale: self.scale,
+            cells,
+            ruleset: self.ruleset.clone(),
+        };
+        let json = serde_json::to_string_pretty(&snapshot)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        fs::write(path, json)
     }
 
-    fn get_index_by_pos(&self, x: usize, y: usize) -> usize {
-        y * self.width + x
+    /// Restores a canvas and ruleset previously written by `save`. Reads
+    /// with json5 so hand-edited saves can use comments and trailing commas.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let data = fs::read_to_string(path)?;
+        let snapshot: WorldSnapshot =
+            json5::from_str(&data).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        Ok(Self::build(
+            snapshot.width,
+            snapshot.height,
+            snapshot.scale,
+            snapshot.cells,
+            snapshot.ruleset,
+        ))
     }
 
-    fn get_cell_by_index(&self, index: usize) -> &Cell {
-        &self.cells[index]
+    fn get_index_by_pos(&self, x: usize, y: usize) -> usize {
+        y * self.width + x
     }
 
-    fn get_cell_by_pos(&self, x: usize, y: usize) -> &Cell {
-        &self.cells[y * self.width + x]
+    fn get_cell_by_pos(&self, x: usize, y: usize) -> Cell {
+        self.cells.get(x, y)
     }
 
     fn set_sell_by_pos(&mut self, x: usize, y: usize, cell: Cell) -> bool {
-        let index = self.get_index_by_pos(x, y);
-        let usefull = self.cells[index].class != cell.class;
-        self.cells[index] = cell;
+        let usefull = self.cells.get(x, y).class != cell.class;
+        self.cells.set(x, y, cell);
         usefull
     }
 
+    /// Reads the cell at a flat grid index, bridging the `usize` bookkeeping
+    /// the rule cache uses to the chunked `World`'s `(x, y)` storage.
+    fn cell_at(&self, index: usize) -> Cell {
+        self.cells.get(index % self.width, index / self.width)
+    }
+
+    /// Writes the cell at a flat grid index; see `cell_at`.
+    fn set_cell_at(&mut self, index: usize, cell: Cell) {
+        self.cells.set(index % self.width, index / self.width, cell);
+    }
+
     fn in_bounds(&self, x: usize, y: usize) -> bool {
         x < self.width && y < self.height
     }
 
-    fn is_empty(&self, x: usize, y: usize) -> bool {
-        if !self.in_bounds(x, y) {
-            return false;
+    /// Bounds-checks and flattens a pattern's footprint anchored at `(x, y)`
+    /// into world cell indices, or `None` if any of it falls off the grid.
+    fn pattern_indices(&self, x: usize, y: usize, pattern: &crate::rule::SubRule) -> Option<Vec<usize>> {
+        let mut indices = Vec::with_capacity(pattern.width * pattern.height);
+        for row in 0..pattern.height {
+            for col in 0..pattern.width {
+                let (px, py) = (x + col, y + row);
+                if !self.in_bounds(px, py) {
+                    return None;
+                }
+                indices.push(self.get_index_by_pos(px, py));
+            }
         }
-        let cell = self.get_cell_by_pos(x, y);
-        cell.class == CellClass::Empty
+        Some(indices)
     }
 
-    // add a move to the changes hashmap
-    fn move_cell(&mut self, x: usize, y: usize, xto: usize, yto: usize) {
-        let index = self.get_index_by_pos(x, y);
-        let index_to = self.get_index_by_pos(xto, yto);
-        let possible_sources = self.changes.entry(index_to).or_insert(Vec::new());
-        possible_sources.push(index);
+    /// Whether `variant`'s pattern currently matches anchored at `(x, y)`,
+    /// against the live `self.cells` (ignores failrate and already-claimed
+    /// cells, both of which only matter at apply time).
+    fn matches_variant(&self, x: usize, y: usize, variant_index: usize) -> bool {
+        let pattern = &self.variants[variant_index].pattern;
+        let Some(indices) = self.pattern_indices(x, y, pattern) else {
+            return false;
+        };
+        pattern
+            .try_match(&self.ruleset.groups, |col, row| {
+                Some(self.cell_at(indices[row * pattern.width + col]).class)
+            })
+            .is_some()
     }
 
-    fn move_down(&mut self, x: usize, y: usize) -> bool {
-        // Calculate destination position.
-        let dest_y = y + 1;
-        if !self.in_bounds(x, dest_y) {
-            return false;
+    /// Drops any cached matches anchored at `(x, y)`, then re-tests every
+    /// variant there and re-inserts whatever currently matches.
+    fn rescan_position(&mut self, x: usize, y: usize) {
+        let index = self.get_index_by_pos(x, y);
+        for entry in std::mem::take(&mut self.match_cache[index]) {
+            if let Some(pos) = self.cache[entry].matches.iter().position(|&a| a == index) {
+                self.cache[entry].matches.swap_remove(pos);
+            }
         }
-
-        let dest_cell = self.get_cell_by_pos(x, dest_y);
-        let current_cell = self.get_cell_by_pos(x, y);
-
-        // If destination is empty or contains water while current cell is sand,
-        // then allow movement.
-        let can_move = dest_cell.class == CellClass::Empty
-            || (current_cell.class == CellClass::Sand && dest_cell.class == CellClass::Water);
-
-        if can_move {
-            // If swapping with water, you might want to do more than just move the sand;
-            // you might want water to move upward or sideways.
-            self.move_cell(x, y, x, dest_y);
+        for variant_index in 0..self.variants.len() {
+            if self.matches_variant(x, y, variant_index) {
+                self.cache[variant_index].matches.push(index);
+                self.match_cache[index].push(variant_index);
+            }
         }
-
-        can_move
     }
 
-    fn move_side(&mut self, x: usize, y: usize) -> bool {
-        let mut left = x > 0 && self.is_empty(x - 1, y);
-        let mut right = self.is_empty(x + 1, y);
-
-        if left && right {
-            left = rand::gen_range(0, 2) == 0;
-            right = !left;
+    /// Grows the simulated bounds by `(extra_width, extra_height)` cells.
+    /// The chunked cell storage needs no work for this — chunks past the old
+    /// bounds already read back as empty until something writes into them —
+    /// only the flat-index-keyed caches, sized to `width * height`, have to
+    /// be rebuilt. This is the growth path the chunked `World` was meant to
+    /// unlock: a bigger playfield without reallocating or copying any cells.
+    pub fn grow(&mut self, extra_width: usize, extra_height: usize) {
+        if extra_width == 0 && extra_height == 0 {
+            return;
         }
-
-        if left {
-            self.move_cell(x, y, x - 1, y);
-        } else if right {
-            self.move_cell(x, y, x + 1, y);
+        self.width += extra_width;
+        self.height += extra_height;
+        self.match_cache = vec![Vec::new(); self.width * self.height];
+        for entry in &mut self.cache {
+            entry.matches.clear();
         }
-
-        left || right
+        self.rescan_all();
     }
 
-    fn move_down_side(&mut self, x: usize, y: usize) -> bool {
-        let mut down_left = x > 0 && self.is_empty(x - 1, y + 1);
-        let mut down_right = self.is_empty(x + 1, y + 1);
-
-        if down_left && down_right {
-            down_left = rand::gen_range(0, 2) == 0;
-            down_right = !down_left;
+    /// Full-grid rescan, used at startup and after `grow` to seed the caches. Every
+    /// position is tested, even ones whose whole neighborhood is empty:
+    /// a ruleset is free to define a rule that matches on all-`Empty`/`Any`
+    /// (spontaneous spawn/generation), and skipping such a position here
+    /// would leave it permanently unmatched since it never changes on its
+    /// own to trigger `invalidate_and_rescan` later.
+    fn rescan_all(&mut self) {
+        for x in 0..self.width {
+            for y in 0..self.height {
+                self.rescan_position(x, y);
+            }
         }
+    }
 
-        if down_left {
-            self.move_cell(x, y, x - 1, y + 1);
-        } else if down_right {
-            self.move_cell(x, y, x + 1, y + 1);
+    /// Re-evaluates every position within `max_rule_width x max_rule_height`
+    /// of `(x, y)`: any match anchored in that neighborhood could have had a
+    /// cell in its footprint change, so it's cheaper to just retest the lot
+    /// than to track exact footprints.
+    fn invalidate_and_rescan(&mut self, x: usize, y: usize) {
+        let reach_x = self.max_rule_width.saturating_sub(1);
+        let reach_y = self.max_rule_height.saturating_sub(1);
+        let from_x = x.saturating_sub(reach_x);
+        let from_y = y.saturating_sub(reach_y);
+        for ry in from_y..=y {
+            for rx in from_x..=x {
+                if self.in_bounds(rx, ry) {
+                    self.rescan_position(rx, ry);
+                }
+            }
         }
-
-        down_left || down_right
     }
 
-    fn move_up(&mut self, x: usize, y: usize) -> bool {
-        if y == 0 {
+    /// Tries to apply the cached match of `variant_index` anchored at
+    /// `anchor`, skipping it if any cell it touches was already claimed by
+    /// another application this tick. Returns whether it fired.
+    fn try_apply_cached(&mut self, anchor: usize, variant_index: usize) -> bool {
+        let variant = &self.variants[variant_index];
+        let pattern = &variant.pattern;
+        let x = anchor % self.width;
+        let y = anchor / self.width;
+
+        let Some(indices) = self.pattern_indices(x, y, pattern) else {
+            return false;
+        };
+        if indices.iter().any(|i| self.changes.contains_key(i)) {
             return false;
         }
-        let up = self.is_empty(x, y - 1);
-        if up {
-            self.move_cell(x, y, x, y - 1);
+        if variant.failrate > 0 && rand::gen_range(0, 256) < variant.failrate as u32 {
+            return false;
         }
-        up
+
+        let snapshot: Vec<CellClass> = indices.iter().map(|&i| self.cell_at(i).class).collect();
+        for (i, class) in pattern.resolve(&snapshot, &self.ruleset.groups).into_iter().enumerate() {
+            if let Some(class) = class {
+                self.changes.insert(indices[i], Cell::of(class));
+                if let Some(source) = pattern.copy_source(i) {
+                    if source != i {
+                        self.moves.insert(indices[i], indices[source]);
+                    }
+                }
+            }
+        }
+        true
     }
 
-    fn move_up_side(&mut self, x: usize, y: usize) -> bool {
-        if y == 0 {
-            return false;
+    fn shuffled(&self, mut positions: Vec<usize>) -> Vec<usize> {
+        for i in (1..positions.len()).rev() {
+            let j = rand::gen_range(0, i as u32 + 1) as usize;
+            positions.swap(i, j);
         }
-        let mut up_left = x > 0 && self.is_empty(x - 1, y - 1);
-        let mut up_right = self.is_empty(x + 1, y - 1);
-        if up_left && up_right {
-            // Randomly choose between left and right when both options are available:
-            up_left = rand::gen_range(0, 2) == 0;
-            up_right = !up_left;
+        positions
+    }
+
+    /// Applies pending changes to the live grid and rescans the affected
+    /// neighborhoods, returning the pixel offset each moved cell should
+    /// animate in from (destination index -> `(dx, dy)` in pixels).
+    fn commit_cells(&mut self) -> BlockOffsets {
+        self.cells.clear_activity();
+        let changed: Vec<usize> = self.changes.keys().copied().collect();
+        let pending: Vec<(usize, Cell)> = self.changes.drain().collect();
+        for (index, cell) in pending {
+            self.set_cell_at(index, cell);
         }
-        if up_left {
-            self.move_cell(x, y, x - 1, y - 1);
-        } else if up_right {
-            self.move_cell(x, y, x + 1, y - 1);
+        self.cells.reclaim_dormant();
+        for &index in &changed {
+            self.invalidate_and_rescan(index % self.width, index / self.width);
         }
-        up_left || up_right
-    }
-
-    pub fn commit_cells(&mut self) {
-        for (destination, possible_sources) in self.changes.iter() {
-            // pick one of the possible sources
-            let source = possible_sources[rand::gen_range(0, possible_sources.len())];
-            if self.cells[source].class == CellClass::Sand && self.cells[*destination].class == CellClass::Water {
-                // Swap the sand and water
-                self.cells.swap(source, *destination);
-            } else {
-                // Normal move: overwrite destination and clear source.
-                self.cells[*destination] = self.cells[source];
-                self.cells[source] = Cell::empty();
+
+        self.moves
+            .drain()
+            .map(|(dest, source)| {
+                let scale = self.scale as i32;
+                let (dest_x, dest_y) = ((dest % self.width) as i32, (dest / self.width) as i32);
+                let (src_x, src_y) = ((source % self.width) as i32, (source / self.width) as i32);
+                (dest, ((src_x - dest_x) * scale, (src_y - dest_y) * scale))
+            })
+            .collect()
+    }
+
+    /// Runs one fixed-rate simulation step: matches, applies and commits the
+    /// cached rules, then hands the resulting moves to `animation` to slide
+    /// in over the next tick.
+    fn step(&mut self) {
+        for entry_index in 0..self.cache.len() {
+            let positions = self.shuffled(self.cache[entry_index].matches.clone());
+            for anchor in positions {
+                self.try_apply_cached(anchor, entry_index);
             }
         }
-        self.changes.clear();
+        let offsets = self.commit_cells();
+        self.animation.start(offsets);
+    }
+
+    /// Advances the simulation by `delta` seconds of real time, stepping the
+    /// fixed-rate sim as many times as have accumulated and progressing the
+    /// render-time animation in between, so falling sand looks smooth at any
+    /// frame rate.
+    pub fn tick(&mut self, delta: f32) {
+        self.tick_accumulator += delta;
+        while self.tick_accumulator >= TICK_DURATION {
+            self.step();
+            self.tick_accumulator -= TICK_DURATION;
+        }
+        self.animation.seed_progress(self.tick_accumulator / TICK_DURATION);
     }
 
     pub fn update(&mut self) {
         if is_key_down(KeyCode::Q) {
-            self.brush = Cell::smoke();
+            self.brush = CellClass::Smoke;
         } else if is_key_down(KeyCode::W) {
-            self.brush = Cell::water();
+            self.brush = CellClass::Water;
         } else if is_key_down(KeyCode::S) {
-            self.brush = Cell::sand();
+            self.brush = CellClass::Sand;
         } else if is_key_down(KeyCode::E) {
-            self.brush = Cell::empty();
+            self.brush = CellClass::Empty;
         } else if is_key_down(KeyCode::R) {
-            self.brush = Cell::rock();
+            self.brush = CellClass::Rock;
+        }
+
+        if is_key_pressed(KeyCode::F5) {
+            if let Err(err) = self.save(Self::SAVE_PATH) {
+                eprintln!("failed to save world to {}: {err}", Self::SAVE_PATH);
+            }
+        } else if is_key_pressed(KeyCode::F9) {
+            match Self::load(Self::SAVE_PATH) {
+                Ok(loaded) => *self = loaded,
+                Err(err) => eprintln!("failed to load world from {}: {err}", Self::SAVE_PATH),
+            }
+        }
+
+        if is_key_pressed(KeyCode::Equal) {
+            self.grow(CHUNK_SIZE, CHUNK_SIZE);
         }
 
         if is_mouse_button_down(MouseButton::Left) {
@@ -264,54 +636,27 @@ impl SandWorld {
             let x = (coords.0 / self.scale as f32) as usize;
             let y = (coords.1 / self.scale as f32) as usize;
             if self.in_bounds(x, y) {
-                // Brush placement now works for all cell types
-                let usefull = self.set_sell_by_pos(x, y, self.brush);
-                self.water_cells += if usefull && self.brush.class == CellClass::Water { 1 } else { 0 };
-            }
-        }
-
-        for x in 0..self.width {
-            for y in (0..self.height).rev() {
-                let cell = self.get_cell_by_pos(x, y);
-                let properties = cell.properties;
-
-                if (properties & CellProperties::MOVEDOWN) != CellProperties::NONE
-                    && self.move_down(x, y)
-                {
-                } else if (properties & CellProperties::MOVEDOWNSIDE) != CellProperties::NONE
-                    && self.move_down_side(x, y)
-                {
-                } else if (properties & CellProperties::MOVESIDE) != CellProperties::NONE
-                    && self.move_side(x, y)
-                {
-                } else if (properties & CellProperties::MOVEUP) != CellProperties::NONE
-                    && self.move_up(x, y)
-                {
-                } else if (properties & CellProperties::MOVEUPSIDE) != CellProperties::NONE
-                    && self.move_up_side(x, y)
-                {
+                let usefull = self.set_sell_by_pos(x, y, Cell::of(self.brush));
+                self.water_cells += if usefull && self.brush == CellClass::Water { 1 } else { 0 };
+                if usefull {
+                    self.invalidate_and_rescan(x, y);
                 }
             }
         }
     }
 
-    pub fn draw(&self) {
-        for (i, cell) in self.cells.iter().enumerate() {
-            let x = (i % self.width) as f32 * self.scale as f32;
-            let y = (i / self.width) as f32 * self.scale as f32;
-            match cell.class {
-                CellClass::Empty => (),
-                CellClass::Sand => {
-                    draw_rectangle(x, y, self.scale as f32, self.scale as f32, YELLOW)
-                }
-                CellClass::Water => {
-                    draw_rectangle(x, y, self.scale as f32, self.scale as f32, BLUE)
-                }
-                CellClass::Rock => draw_rectangle(x, y, self.scale as f32, self.scale as f32, GRAY),
-                CellClass::Smoke => {
-                    draw_rectangle(x, y, self.scale as f32, self.scale as f32, LIGHTGRAY)
-                }
-            }
+    /// Hands the live grid to `renderer` to draw, however it sees fit.
+    pub fn draw(&self, renderer: &mut dyn Renderer) {
+        renderer.render(self.grid_view());
+    }
+
+    fn grid_view(&self) -> GridView<'_> {
+        GridView {
+            width: self.width,
+            height: self.height,
+            scale: self.scale,
+            cells: &self.cells,
+            offsets: self.animation.current_offsets(),
         }
     }
 
@@ -320,7 +665,7 @@ impl SandWorld {
     }
 
     pub fn brush(&self) -> &str {
-        match self.brush.class {
+        match self.brush {
             CellClass::Empty => "Empty",
             CellClass::Sand => "Sand",
             CellClass::Water => "Water",
@@ -329,3 +674,80 @@ impl SandWorld {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rule::{Rule, RuleCellFrom, RuleCellTo, SubRule};
+
+    /// A single rule that swaps a `Sand` cell down into an `Empty` one below
+    /// it, same shape as the first rule in `assets/default_ruleset.json`.
+    fn falling_sand_ruleset() -> Ruleset {
+        Ruleset {
+            groups: Vec::new(),
+            rules: vec![Rule {
+                pattern: SubRule {
+                    width: 1,
+                    height: 2,
+                    cells: vec![
+                        (RuleCellFrom::One(CellClass::Sand), RuleCellTo::Copy(1)),
+                        (RuleCellFrom::One(CellClass::Empty), RuleCellTo::Copy(0)),
+                    ],
+                },
+                flip_x: false,
+                flip_y: false,
+                rotate: false,
+                failrate: 0,
+            }],
+        }
+    }
+
+    /// Each cache entry's matches, sorted so two snapshots taken after
+    /// different scan paths can be compared regardless of insertion order.
+    fn snapshot_cache(world: &SandWorld) -> Vec<Vec<usize>> {
+        world
+            .cache
+            .iter()
+            .map(|entry| {
+                let mut matches = entry.matches.clone();
+                matches.sort_unstable();
+                matches
+            })
+            .collect()
+    }
+
+    #[test]
+    fn incremental_rescan_matches_a_full_rescan() {
+        let (width, height) = (6, 6);
+        let mut cells = vec![Cell::empty(); width * height];
+        cells[width + 2] = Cell::of(CellClass::Sand);
+
+        let mut world = SandWorld::build(width, height, 4, cells, falling_sand_ruleset());
+        world.step();
+
+        let incremental = snapshot_cache(&world);
+        world.rescan_all();
+        let full = snapshot_cache(&world);
+
+        assert_eq!(incremental, full);
+    }
+
+    #[test]
+    fn save_then_load_round_trips_cells_and_ruleset() {
+        let world = SandWorld::new(10, 10, 5);
+        let path = std::env::temp_dir().join(format!("autocel_test_{:p}.json5", &world as *const SandWorld));
+
+        world.save(&path).expect("save should succeed");
+        let loaded = SandWorld::load(&path).expect("load should succeed");
+        fs::remove_file(&path).ok();
+
+        assert_eq!(loaded.width, world.width);
+        assert_eq!(loaded.height, world.height);
+        assert_eq!(loaded.scale, world.scale);
+        assert_eq!(
+            loaded.cells.live_cells(loaded.width, loaded.height).collect::<Vec<_>>(),
+            world.cells.live_cells(world.width, world.height).collect::<Vec<_>>()
+        );
+        assert_eq!(loaded.ruleset.rules.len(), world.ruleset.rules.len());
+    }
+}